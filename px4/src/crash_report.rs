@@ -0,0 +1,152 @@
+//! Writes a self-contained crash report when a module panics, in the same
+//! spirit as [human-panic](https://docs.rs/human-panic): the PX4 console's
+//! scrollback is ephemeral, so a raw backtrace printed to it is gone the
+//! moment the operator's session ends. A durable file next to the ULog
+//! session gives them something to pull off the SD card afterwards.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::PanicInfo;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::logging::{log_raw, panic_message, LogLevel};
+use crate::{module_name, ModuleContext};
+
+/// Directory crash reports are written to: a subdirectory of the one PX4
+/// logs ULog sessions under, so they end up on the same SD card pull.
+const LOG_DIR: &str = "/fs/microsd/log/crash_reports";
+
+pub(crate) fn write_report(info: &PanicInfo, context: Option<&ModuleContext>) {
+	let modulename = context
+		.map(|context| module_name(context.modulename))
+		.unwrap_or("<unknown module>");
+	let version = context.map(|context| context.version).unwrap_or("<unknown version>");
+	let args = context.map(|context| context.args.as_slice()).unwrap_or(&[]);
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or(0);
+
+	let message = panic_message(info);
+	let location = info.location().map(|location| (location.file(), location.line(), location.column()));
+	let backtrace = format!("{}", Backtrace::force_capture());
+	let report = render_report(modulename, version, args, timestamp, &message, location, &backtrace);
+	// Worker threads spawned via `task::spawn` can panic around the same
+	// moment, so the thread id disambiguates reports that would otherwise
+	// collide (and silently truncate one another) at whole-second resolution.
+	let path = format!("{}/{}-{}-{:?}.toml", LOG_DIR, modulename, timestamp, thread::current().id());
+
+	if let Err(err) = fs::create_dir_all(LOG_DIR) {
+		log_raw(
+			LogLevel::Error,
+			format_args!(
+				"'{}' panicked, and creating the crash report directory '{}' failed: {}",
+				modulename, LOG_DIR, err
+			),
+		);
+		return;
+	}
+
+	match fs::write(&path, report) {
+		Ok(()) => log_raw(
+			LogLevel::Error,
+			format_args!("'{}' panicked; a crash report was written to '{}'", modulename, path),
+		),
+		Err(err) => log_raw(
+			LogLevel::Error,
+			format_args!(
+				"'{}' panicked, and writing a crash report to '{}' failed: {}",
+				modulename, path, err
+			),
+		),
+	}
+}
+
+fn render_report(
+	modulename: &str,
+	version: &str,
+	args: &[String],
+	timestamp: u64,
+	message: &str,
+	location: Option<(&str, u32, u32)>,
+	backtrace: &str,
+) -> String {
+	let mut report = String::new();
+
+	// All of these are top-level `key = value` pairs, and so must come
+	// before the `[location]` table header below: TOML attaches every bare
+	// `key = value` line to whichever table header precedes it, so anything
+	// meant to be top-level has to be written first.
+	let _ = writeln!(report, "name = {:?}", modulename);
+	let _ = writeln!(report, "version = {:?}", version);
+	let _ = writeln!(report, "timestamp = {}", timestamp);
+	let _ = writeln!(report, "args = {:?}", args);
+	let _ = writeln!(report, "message = {:?}", message);
+	let _ = writeln!(report, "backtrace = {:?}", backtrace);
+
+	let _ = writeln!(report, "[location]");
+	if let Some((file, line, column)) = location {
+		let _ = writeln!(report, "file = {:?}", file);
+		let _ = writeln!(report, "line = {}", line);
+		let _ = writeln!(report, "column = {}", column);
+	}
+
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// This crate has no `toml` dependency to parse these reports back with,
+	// so these checks stay at the string level: every value we interpolate
+	// is escaped with `{:?}`, so none of them should ever be able to smuggle
+	// an unescaped `"` into the file and break the line it's on.
+	fn assert_all_lines_well_formed(report: &str) {
+		for line in report.lines() {
+			if line.starts_with('[') {
+				assert!(line.ends_with(']'), "malformed table header: {:?}", line);
+				continue;
+			}
+			if line.is_empty() {
+				continue;
+			}
+			let (_, value) = line.split_once(" = ").unwrap_or_else(|| panic!("not a `key = value` line: {:?}", line));
+			if let Some(inner) = value.strip_prefix('"') {
+				let inner = inner.strip_suffix('"').unwrap_or_else(|| panic!("unterminated string: {:?}", line));
+				let mut escaped = false;
+				for c in inner.chars() {
+					assert!(escaped || c != '"', "unescaped quote broke the line: {:?}", line);
+					escaped = !escaped && c == '\\';
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn escapes_special_characters_in_every_field() {
+		let args = vec!["--name".to_string(), "a \"quoted\" arg".to_string()];
+		let report = render_report(
+			"module \"with\" quotes",
+			"1.0.0 \"release\"",
+			&args,
+			0,
+			"assertion failed: `\"left\"` != `\"right\"`",
+			Some(("src/main.rs", 12, 5)),
+			"line one\nline two",
+		);
+		assert_all_lines_well_formed(&report);
+	}
+
+	#[test]
+	fn location_table_comes_after_every_top_level_field() {
+		let report = render_report("mymodule", "1.0.0", &[], 0, "boom", Some(("src/main.rs", 1, 1)), "");
+		let location_header = report.find("[location]").expect("missing [location] header");
+		for key in ["name", "version", "timestamp", "args", "message", "backtrace"] {
+			let key_pos = report.find(&format!("{} = ", key)).unwrap_or_else(|| panic!("missing field {:?}", key));
+			assert!(key_pos < location_header, "{:?} was written after [location]", key);
+		}
+	}
+}
@@ -39,7 +39,9 @@
 //!
 //! Your main function should take a `&[&str]` as argument. It *may* return a
 //! `i32` status code, either directly, or as the error type of a `Result`.  A
-//! panic from your main thread is caught and results in a status code of −1.
+//! panic from your main thread is caught and results in a status code of −1,
+//! unless it was raised with [`std::panic::panic_any`] carrying an
+//! [`ExitCode`], in which case that code is used instead.
 //!
 //! ### Example
 //!
@@ -106,23 +108,85 @@
 //! }
 //! ```
 
+use std::cell::RefCell;
 use std::ffi::CStr;
 use std::os::raw::c_char;
 
+mod crash_report;
 mod logging;
+pub mod task;
 pub mod uorb;
 
 pub use crate::logging::{log_raw, LogLevel};
 pub use px4_macros::{px4_message, px4_module_main};
 
+/// Identifies the module and invocation a panic happened in, so the panic
+/// hook can attribute it correctly even though it only ever sees the
+/// `PanicInfo`.
+///
+/// Populated by [`_run`] before the module's main function runs (and by
+/// [`task::spawn`] on worker threads), and read back out by the panic hook
+/// and [`crash_report`].
 #[doc(hidden)]
-pub unsafe fn _run<F, R>(modulename: &'static [u8], argc: u32, argv: *mut *mut u8, f: F) -> i32
+#[derive(Clone)]
+pub struct ModuleContext {
+	pub modulename: &'static [u8],
+	pub version: &'static str,
+	pub args: Vec<String>,
+}
+
+thread_local! {
+	static CONTEXT: RefCell<Option<ModuleContext>> = RefCell::new(None);
+}
+
+/// Gives `f` access to the context of the module currently running on this
+/// thread, if any. There is none before [`_run`] has started, or on a thread
+/// that wasn't started through `_run` or [`task::spawn`].
+pub(crate) fn with_current_context<T>(f: impl FnOnce(Option<&ModuleContext>) -> T) -> T {
+	CONTEXT.with(|context| f(context.borrow().as_ref()))
+}
+
+/// Sets the context for the current thread, e.g. after handing a clone of
+/// the spawning thread's context to a [`task::spawn`]ed worker.
+pub(crate) fn set_current_context(context: Option<ModuleContext>) {
+	CONTEXT.with(|cell| *cell.borrow_mut() = context);
+}
+
+/// Extracts the module name from the nul-terminated byte string `_run` is
+/// called with, for display in logs and crash reports.
+pub(crate) fn module_name(modulename: &[u8]) -> &str {
+	CStr::from_bytes_with_nul(modulename)
+		.ok()
+		.and_then(|modulename| modulename.to_str().ok())
+		.unwrap_or("<invalid module name>")
+}
+
+#[doc(hidden)]
+pub unsafe fn _run<F, R>(
+	modulename: &'static [u8],
+	version: &'static str,
+	argc: u32,
+	argv: *mut *mut u8,
+	f: F,
+) -> i32
 where
 	F: Fn(&[&str]) -> R + std::panic::UnwindSafe,
 	R: MainStatusCode,
 {
 	logging::init(modulename);
 	std::panic::catch_unwind(move || {
+		// Set the context before parsing argv, not after: a malformed
+		// argument panics out of the loop below, and the panic hook should
+		// still be able to attribute that to this module, not
+		// `<unknown module>`.
+		CONTEXT.with(|context| {
+			*context.borrow_mut() = Some(ModuleContext {
+				modulename,
+				version,
+				args: Vec::new(),
+			});
+		});
+
 		let mut args = Vec::with_capacity(argc as usize);
 		for i in 0..argc {
 			args.push(
@@ -131,10 +195,42 @@ where
 					.unwrap_or_else(|_| panic!("Invalid UTF-8 in arguments.")),
 			);
 		}
+		CONTEXT.with(|context| {
+			if let Some(context) = context.borrow_mut().as_mut() {
+				context.args = args.iter().map(|arg| arg.to_string()).collect();
+			}
+		});
 		f(&args).to_status_code()
-	}).unwrap_or(R::panic_status_code())
+	})
+	.unwrap_or_else(|payload| {
+		payload
+			.downcast::<ExitCode>()
+			.map(|exit_code| exit_code.0)
+			.unwrap_or_else(|_| R::panic_status_code())
+	})
 }
 
+/// A typed panic payload, letting your main function communicate a specific
+/// exit status code instead of falling back to `R::panic_status_code()`.
+///
+/// This is a structured-abort path for a deliberate, controlled exit, not a
+/// way to report a bug: the panic hook recognizes it and only logs a short
+/// note of the exit status, skipping the error-level log line and crash
+/// report a genuine panic gets.
+///
+/// Panic with it via [`std::panic::panic_any`], not the `panic!` macro (which
+/// requires a `Display` payload):
+///
+/// ```
+/// use px4::{px4_module_main, ExitCode};
+///
+/// #[px4_module_main]
+/// fn my_module(args: &[&str]) -> i32 {
+///   std::panic::panic_any(ExitCode(42))
+/// }
+/// ```
+pub struct ExitCode(pub i32);
+
 /// The return type of your `#[px4_module_main]` function.
 pub trait MainStatusCode {
 	/// The status code to return.
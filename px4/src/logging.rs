@@ -0,0 +1,137 @@
+//! Bridges the standard [`log`](https://docs.rs/log/) crate to PX4's own
+//! logging facilities (`PX4_INFO`, `PX4_WARN`, `PX4_ERR`, ... in C/C++).
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::PanicInfo;
+use std::sync::Once;
+
+use log::{Level, Log, Metadata, Record};
+
+extern "C" {
+	fn px4_log_modulename(name: *const c_char);
+	fn px4_log_raw(level: i32, msg: *const c_char);
+}
+
+/// The severity a message is logged at, mirroring PX4's own log levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum LogLevel {
+	Debug = 0,
+	Info = 1,
+	Warn = 2,
+	Error = 3,
+}
+
+impl From<Level> for LogLevel {
+	fn from(level: Level) -> Self {
+		match level {
+			Level::Error => LogLevel::Error,
+			Level::Warn => LogLevel::Warn,
+			Level::Info => LogLevel::Info,
+			Level::Debug | Level::Trace => LogLevel::Debug,
+		}
+	}
+}
+
+struct Px4Logger;
+
+impl Log for Px4Logger {
+	fn enabled(&self, _metadata: &Metadata) -> bool {
+		true
+	}
+
+	fn log(&self, record: &Record) {
+		log_raw(record.level().into(), format_args!("{}", record.args()));
+	}
+
+	fn flush(&self) {}
+}
+
+static LOGGER: Px4Logger = Px4Logger;
+static INIT_PANIC_HOOK: Once = Once::new();
+
+/// Sets up the `log` crate to forward to PX4, and installs a panic hook that
+/// does the same for panic messages.
+///
+/// Called once at the start of [`_run`](crate::_run), before the module's
+/// main function is invoked.
+pub(crate) unsafe fn init(modulename: &'static [u8]) {
+	px4_log_modulename(modulename.as_ptr() as *const c_char);
+
+	// `set_logger` fails if a logger is already installed; that's fine, it
+	// just means an earlier module in this process already did it.
+	let _ = log::set_logger(&LOGGER);
+	log::set_max_level(log::LevelFilter::Trace);
+
+	install_panic_hook();
+}
+
+/// Installs a panic hook that logs through PX4 instead of stderr, which PX4
+/// redirects away from the operator's terminal. Only the first call actually
+/// installs anything; later calls (from other modules sharing this process)
+/// are no-ops, and the previously installed hook — whatever it was — is
+/// chained so it still runs afterwards.
+fn install_panic_hook() {
+	INIT_PANIC_HOOK.call_once(|| {
+		let previous_hook = std::panic::take_hook();
+		std::panic::set_hook(Box::new(move |info| {
+			// A panic carrying an `ExitCode` is a module's deliberate,
+			// structured-abort path rather than a bug, so it only gets a
+			// short note instead of the full error-level log line and crash
+			// report a genuine panic gets.
+			if let Some(exit_code) = info.payload().downcast_ref::<crate::ExitCode>() {
+				log_raw(LogLevel::Info, format_args!("exiting with status code {}", exit_code.0));
+			} else {
+				log_panic(info);
+				crate::with_current_context(|context| crate::crash_report::write_report(info, context));
+			}
+			previous_hook(info);
+		}));
+	});
+}
+
+fn log_panic(info: &PanicInfo) {
+	let message = panic_message(info);
+	match info.location() {
+		Some(location) => log_raw(
+			LogLevel::Error,
+			format_args!(
+				"panicked at '{}', {}:{}:{}",
+				message,
+				location.file(),
+				location.line(),
+				location.column()
+			),
+		),
+		None => log_raw(LogLevel::Error, format_args!("panicked at '{}'", message)),
+	}
+}
+
+/// Extracts the panic message from a [`PanicInfo`], for the common cases of
+/// a `&str` or `String` payload (what `panic!` produces).
+pub(crate) fn panic_message(info: &PanicInfo) -> String {
+	let payload = info.payload();
+	payload
+		.downcast_ref::<&str>()
+		.copied()
+		.map(str::to_string)
+		.or_else(|| payload.downcast_ref::<String>().cloned())
+		.unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+/// Sends a raw, unformatted line to the PX4 console, equivalent to the
+/// `PX4_INFO_RAW` (etc.) macros in C and C++.
+///
+/// Do not use standard output or standard error for this, as the standard
+/// streams of the PX4 process are often not the ones connected to the
+/// terminal the user is looking at.
+pub fn log_raw(level: LogLevel, msg: std::fmt::Arguments) {
+	let msg = match CString::new(msg.to_string()) {
+		Ok(msg) => msg,
+		Err(_) => return,
+	};
+	unsafe {
+		px4_log_raw(level as i32, msg.as_ptr());
+	}
+}
@@ -0,0 +1,58 @@
+//! Panic-isolated thread spawning for a module's background workers.
+//!
+//! [`_run`](crate::_run) already catches panics on a module's main thread,
+//! but a background thread (e.g. polling a uORB topic) panicking would
+//! bypass that entirely: there's nothing there to catch it or report it
+//! through PX4 logging. [`spawn`] gives worker threads the same treatment,
+//! under a thread name derived from the module.
+
+use std::panic::{catch_unwind, UnwindSafe};
+use std::thread::{self, JoinHandle};
+
+use crate::{module_name, set_current_context, with_current_context};
+
+/// The outcome of a task spawned with [`spawn`].
+pub enum TaskResult<T> {
+	/// The closure returned normally.
+	Finished(T),
+	/// The closure panicked. By the time you observe this, the panic has
+	/// already been logged through the same path as a panic on the module's
+	/// main thread.
+	Panicked,
+}
+
+impl<T> TaskResult<T> {
+	/// Whether the task panicked instead of returning normally.
+	pub fn panicked(&self) -> bool {
+		matches!(self, TaskResult::Panicked)
+	}
+}
+
+/// Spawns `f` on a new thread named after the current module, with panics
+/// caught and routed through PX4 logging instead of silently killing the
+/// thread.
+///
+/// Returns a [`JoinHandle`] whose result tells you whether the task
+/// panicked, so a supervisor can decide to restart it.
+pub fn spawn<F, T>(f: F) -> JoinHandle<TaskResult<T>>
+where
+	F: FnOnce() -> T + Send + UnwindSafe + 'static,
+	T: Send + 'static,
+{
+	let context = with_current_context(|context| context.cloned());
+	let name = context
+		.as_ref()
+		.map(|context| module_name(context.modulename).to_string())
+		.unwrap_or_else(|| "px4-worker".to_string());
+
+	thread::Builder::new()
+		.name(name)
+		.spawn(move || {
+			set_current_context(context);
+			match catch_unwind(f) {
+				Ok(value) => TaskResult::Finished(value),
+				Err(_) => TaskResult::Panicked,
+			}
+		})
+		.expect("failed to spawn px4 worker thread")
+}